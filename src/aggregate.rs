@@ -1,37 +1,83 @@
 use std::marker::PhantomData;
+use std::ops::{Add, Sub};
 
-use crate::interval::Interval;
+use crate::interval::{Interval, Timestamp};
 
-pub trait Aggregate {
+pub trait Aggregate<T = Timestamp> {
     type Value;
     fn empty() -> Self;
-    fn initial(interval: &Interval, value: &Self::Value) -> Self;
+    fn initial(interval: &Interval<T>, value: &Self::Value) -> Self;
     fn aggregate(&mut self, other: &Self);
     fn weight(&mut self, _weight: f32) {}
 }
 
-pub struct DefaultStatistics<T> {
-    pub min: u64,
-    pub max: u64,
-    pub total_duration: u64,
+/// A coordinate type that can be turned into a duration (`end - start`),
+/// merged across intervals and scaled by a fractional weight. This is what
+/// `DefaultStatistics` needs from `T` to track min/max/total duration for
+/// coordinate types other than the default `Timestamp`.
+///
+/// Implemented for the integer coordinate types `Interval<T>` actually
+/// supports (see `Interval`'s doc comment on why `f64` is excluded).
+pub trait Duration: Copy + Ord + Default + Sub<Output = Self> + Add<Output = Self> {
+    fn to_f64(self) -> f64;
+    fn from_f64(value: f64) -> Self;
+}
+
+impl Duration for u32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as u32
+    }
+}
+
+impl Duration for u64 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as u64
+    }
+}
+
+impl Duration for i64 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as i64
+    }
+}
+
+pub struct DefaultStatistics<V, T = Timestamp> {
+    pub min: T,
+    pub max: T,
+    pub total_duration: T,
     pub count: usize,
-    phantom: PhantomData<T>,
+    phantom: PhantomData<V>,
 }
 
-impl<T> Aggregate for DefaultStatistics<T> {
-    type Value = T;
+impl<V, T> Aggregate<T> for DefaultStatistics<V, T>
+where
+    T: Duration,
+{
+    type Value = V;
 
     fn empty() -> Self {
         Self {
-            min: 0,
-            max: 0,
-            total_duration: 0,
+            min: T::default(),
+            max: T::default(),
+            total_duration: T::default(),
             count: 0,
             phantom: PhantomData,
         }
     }
 
-    fn initial(interval: &Interval, _: &Self::Value) -> Self {
+    fn initial(interval: &Interval<T>, _: &Self::Value) -> Self {
         let duration = interval.end - interval.start;
         Self {
             min: duration,
@@ -46,11 +92,147 @@ impl<T> Aggregate for DefaultStatistics<T> {
         self.min = other.min.min(self.min);
         self.max = other.max.max(self.max);
         self.count += other.count;
-        self.total_duration += other.total_duration;
+        self.total_duration = self.total_duration + other.total_duration;
     }
 
     fn weight(&mut self, weight: f32) {
-        let duration = self.total_duration;
-        self.total_duration = (duration as f64 * weight as f64) as u64;
+        let duration = self.total_duration.to_f64();
+        self.total_duration = T::from_f64(duration * weight as f64);
+    }
+}
+
+/// Number of log-scale buckets kept per histogram. `2^64` durations at
+/// `BASE = 2` already overflows every coordinate type this crate ships
+/// with, so this is generous headroom rather than a tunable.
+const HISTOGRAM_BUCKETS: usize = 64;
+
+/// Approximate percentile aggregate backed by a fixed-boundary, log-scale
+/// histogram of interval durations, in the spirit of measureme's
+/// distribution summaries rather than a single sum.
+///
+/// Bucket `k` covers durations in `[BASE^k, BASE^(k+1))` (`BASE` defaults
+/// to 2). `aggregate` is an elementwise sum of bucket counts, so it stays
+/// O(buckets) and fully mergeable — exactly what the fast-lane
+/// pre-aggregation needs from an `Aggregate`. A duration of zero is placed
+/// in bucket 0 alongside `[1, BASE)`.
+pub struct HistogramStatistics<V, T = Timestamp, const BASE: u32 = 2> {
+    counts: [u64; HISTOGRAM_BUCKETS],
+    phantom: PhantomData<(V, T)>,
+}
+
+impl<V, T, const BASE: u32> HistogramStatistics<V, T, BASE> {
+    fn bucket_of(duration: f64) -> usize {
+        if duration < 1.0 {
+            return 0;
+        }
+        let bucket = duration.log(BASE as f64).floor();
+        (bucket.max(0.0) as usize).min(HISTOGRAM_BUCKETS - 1)
+    }
+
+    /// Approximate the `q`th quantile (`0.0..=1.0`) of the recorded
+    /// interval durations by walking cumulative bucket counts to the
+    /// target rank and linearly interpolating within the straddling
+    /// bucket's `[BASE^k, BASE^(k+1))` range.
+    pub fn quantile(&self, q: f64) -> u64 {
+        let total: u64 = self.counts.iter().sum();
+        if total == 0 {
+            return 0;
+        }
+
+        let target = (q * total as f64).ceil().max(1.0) as u64;
+        let mut cumulative = 0u64;
+
+        for (bucket, &count) in self.counts.iter().enumerate() {
+            cumulative += count;
+            if cumulative < target {
+                continue;
+            }
+
+            let lower = (BASE as f64).powi(bucket as i32);
+            let upper = (BASE as f64).powi(bucket as i32 + 1);
+            let rank_in_bucket = (target - (cumulative - count)) as f64;
+            let fraction = rank_in_bucket / count as f64;
+
+            return (lower + (upper - lower) * fraction) as u64;
+        }
+
+        0
+    }
+}
+
+impl<V, T, const BASE: u32> Aggregate<T> for HistogramStatistics<V, T, BASE>
+where
+    T: Duration,
+{
+    type Value = V;
+
+    fn empty() -> Self {
+        Self {
+            counts: [0; HISTOGRAM_BUCKETS],
+            phantom: PhantomData,
+        }
+    }
+
+    fn initial(interval: &Interval<T>, _: &Self::Value) -> Self {
+        let duration = (interval.end - interval.start).to_f64();
+        let mut counts = [0; HISTOGRAM_BUCKETS];
+        counts[Self::bucket_of(duration)] = 1;
+        Self {
+            counts,
+            phantom: PhantomData,
+        }
+    }
+
+    fn aggregate(&mut self, other: &Self) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other;
+        }
+    }
+
+    fn weight(&mut self, weight: f32) {
+        for count in &mut self.counts {
+            *count = (*count as f64 * weight as f64) as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Hist = HistogramStatistics<u64>;
+
+    #[test]
+    fn test_quantile_of_empty_histogram_is_zero() {
+        let hist = Hist::empty();
+        assert_eq!(hist.quantile(0.5), 0);
+    }
+
+    #[test]
+    fn test_quantile_interpolates_within_a_single_bucket() {
+        let mut hist = Hist::empty();
+        // Durations 2 and 3 both land in bucket 1 ([2, 4)).
+        hist.aggregate(&Hist::initial(&Interval::new(0u64, 2), &0));
+        hist.aggregate(&Hist::initial(&Interval::new(0u64, 3), &0));
+
+        let p50 = hist.quantile(0.5);
+        assert!((2..4).contains(&p50), "p50 = {}", p50);
+    }
+
+    #[test]
+    fn test_quantile_p50_and_p99_track_a_spread_distribution() {
+        let mut hist = Hist::empty();
+        for duration in 1..=1000u64 {
+            hist.aggregate(&Hist::initial(&Interval::new(0u64, duration), &0));
+        }
+
+        let p50 = hist.quantile(0.5);
+        let p99 = hist.quantile(0.99);
+
+        assert!(p50 < p99, "p50 = {}, p99 = {}", p50, p99);
+        // True p50/p99 are ~500/~990; log-scale buckets keep the estimate
+        // within the same order of magnitude.
+        assert!((300..800).contains(&p50), "p50 = {}", p50);
+        assert!((700..1100).contains(&p99), "p99 = {}", p99);
     }
 }
@@ -3,10 +3,10 @@ use std::marker::PhantomData;
 use std::path::Path;
 
 use crate::aggregate::Aggregate;
-use crate::interval::Interval;
+use crate::interval::{Interval, Timestamp};
 
-pub struct BaselineIntervalIndex<V, A> {
-    intervals: Vec<Interval>,
+pub struct BaselineIntervalIndex<V, A, T = Timestamp> {
+    intervals: Vec<Interval<T>>,
     values: Vec<V>,
     phantom: PhantomData<A>,
 }
@@ -24,9 +24,10 @@ where
         .collect()
 }
 
-impl<V, A> BaselineIntervalIndex<V, A>
+impl<V, A, T> BaselineIntervalIndex<V, A, T>
 where
-    A: Aggregate<Value = V>,
+    T: Copy + Ord,
+    A: Aggregate<T, Value = V>,
 {
     pub fn new() -> Self {
         Self {
@@ -38,7 +39,7 @@ where
 
     pub fn push<I>(&mut self, interval: I, value: V)
     where
-        I: Into<Interval>,
+        I: Into<Interval<T>>,
     {
         self.intervals.push(interval.into());
         self.values.push(value);
@@ -46,7 +47,7 @@ where
 
     pub fn query<I>(&self, window: I) -> Vec<&V>
     where
-        I: Into<Interval>,
+        I: Into<Interval<T>>,
     {
         let window = window.into();
         let mut output = vec![];
@@ -65,7 +66,7 @@ where
 
     pub fn aggregate<I>(&self, window: I) -> A
     where
-        I: Into<Interval>,
+        I: Into<Interval<T>>,
     {
         let window = window.into();
         let mut out = A::empty();
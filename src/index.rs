@@ -1,32 +1,62 @@
 use std::marker::PhantomData;
 use std::mem::size_of;
-use std::ops::Range;
+use std::ops::{Range, Sub};
 
-use crate::aggregate::Aggregate;
-use crate::interval::Interval;
+use crate::aggregate::{Aggregate, Duration};
+use crate::interval::{Interval, Timestamp};
 
-pub trait QueryVisitor<V, A> {
-    fn visit_fast_lane(&mut self, lane: &FastLane<V, A>, index: usize);
-    fn visit_slow_lane(&mut self, lane: &SlowLane<V>, index: usize);
+pub trait QueryVisitor<V, A, T = Timestamp> {
+    fn visit_fast_lane(&mut self, lane: &FastLane<V, A, T>, index: usize);
+    fn visit_slow_lane(&mut self, lane: &SlowLane<V, T>, index: usize);
 }
 
-pub struct IntervalIndex<V, A> {
+pub struct IntervalIndex<V, A, T = Timestamp> {
     pub order: usize,
     pub max_top_level: usize,
-    pub fast_lanes: Vec<FastLane<V, A>>,
-    pub slow_lane: SlowLane<V>,
+    pub fast_lanes: Vec<FastLane<V, A, T>>,
+    pub slow_lane: SlowLane<V, T>,
 }
 
+/// Scan state carried between successive `query_from`/`aggregate_from`
+/// calls on the same index.
+///
+/// For a stream of windows with non-decreasing starts, a cursor lets each
+/// call resume scanning from where the previous one left off instead of
+/// re-running `first_overlap` from the top of the fast lanes. Passing a
+/// fresh `Cursor` is always correct, just slower on the first call.
 #[derive(Clone, Debug)]
-pub struct FastLane<V, A> {
+pub struct Cursor<T = Timestamp> {
+    window: Option<Interval<T>>,
+    fast_lane_count: usize,
+    index: usize,
+}
+
+impl<T> Default for Cursor<T> {
+    fn default() -> Self {
+        Self {
+            window: None,
+            fast_lane_count: 0,
+            index: 0,
+        }
+    }
+}
+
+impl<T> Cursor<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FastLane<V, A, T = Timestamp> {
     pub interval: usize,
-    intervals: Vec<Interval>,
+    intervals: Vec<Interval<T>>,
     aggregations: Vec<A>,
     phantom: PhantomData<V>,
 }
 
-pub struct SlowLane<V> {
-    intervals: Vec<Interval>,
+pub struct SlowLane<V, T = Timestamp> {
+    intervals: Vec<Interval<T>>,
     values: Vec<V>,
 }
 
@@ -35,17 +65,31 @@ struct AggregateVisitor<V, A> {
     phantom: PhantomData<V>,
 }
 
-struct RangeVisitor<'a, V> {
+struct RangeVisitor<'a, V, T = Timestamp> {
     count: usize,
-    slow_lane: &'a SlowLane<V>,
+    slow_lane: &'a SlowLane<V, T>,
     output: Vec<Range<usize>>,
 }
 
-impl<V, A> FastLane<V, A>
+struct WeightedAggregateVisitor<V, A, T> {
+    window: Interval<T>,
+    output: A,
+    phantom: PhantomData<V>,
+}
+
+struct CoverageVisitor<'a, V, T> {
+    window: Interval<T>,
+    slow_lane: &'a SlowLane<V, T>,
+    current: Option<Interval<T>>,
+    total: T,
+}
+
+impl<V, A, T> FastLane<V, A, T>
 where
-    A: Aggregate<Value = V>,
+    T: Copy + Ord,
+    A: Aggregate<T, Value = V>,
 {
-    pub fn new(interval: usize, capacity: usize) -> FastLane<V, A> {
+    pub fn new(interval: usize, capacity: usize) -> FastLane<V, A, T> {
         FastLane {
             interval,
             intervals: Vec::with_capacity(capacity),
@@ -58,35 +102,36 @@ where
         self.intervals.len()
     }
 
-    fn push(&mut self, interval: Interval, aggregate: A) {
+    fn push(&mut self, interval: Interval<T>, aggregate: A) {
         self.intervals.push(interval);
         self.aggregations.push(aggregate);
     }
 
-    fn update(&mut self, index: usize, interval: Interval, aggregate: A) {
+    fn update(&mut self, index: usize, interval: Interval<T>, aggregate: A) {
         let other = &mut self.intervals[index];
         other.end = other.end.max(interval.end);
         self.aggregations[index].aggregate(&aggregate);
     }
 }
 
-impl<V> SlowLane<V> {
+impl<V, T> SlowLane<V, T> {
     fn len(&self) -> usize {
         self.intervals.len()
     }
 
-    fn push(&mut self, interval: Interval, value: V) {
+    fn push(&mut self, interval: Interval<T>, value: V) {
         self.intervals.push(interval);
         self.values.push(value);
     }
 }
 
-impl<V, A> IntervalIndex<V, A>
+impl<V, A, T> IntervalIndex<V, A, T>
 where
-    A: Aggregate<Value = V>,
+    T: Copy + Ord + Sub<Output = T>,
+    A: Aggregate<T, Value = V>,
 {
     pub fn new(order: usize) -> Self {
-        let max_top_level = 4096 / size_of::<Interval>();
+        let max_top_level = 4096 / size_of::<Interval<T>>();
 
         let slow_lane = SlowLane {
             // If we expect N elements in the initial fast lane, then we are
@@ -107,7 +152,7 @@ where
 
     pub fn push<I>(&mut self, interval: I, value: V)
     where
-        I: Into<Interval>,
+        I: Into<Interval<T>>,
     {
         let interval = interval.into();
         let index = self.slow_lane.len();
@@ -149,7 +194,7 @@ where
         self.fast_lanes.insert(0, fast_lane);
     }
 
-    fn first_fastlane_overlap(&self, window: Interval) -> usize {
+    fn first_fastlane_overlap(&self, window: Interval<T>) -> usize {
         let mut offset = 0;
 
         for lane in &self.fast_lanes {
@@ -165,7 +210,7 @@ where
         offset
     }
 
-    fn first_overlap(&self, window: Interval) -> usize {
+    fn first_overlap(&self, window: Interval<T>) -> usize {
         let index = self.first_fastlane_overlap(window);
         let slice = &self.slow_lane.intervals[index..];
 
@@ -178,21 +223,131 @@ where
         index
     }
 
+    /// Resolve the slow-lane index to start scanning from for `window`,
+    /// reusing `cursor` when it was built against a window that doesn't
+    /// start after `window`.
+    ///
+    /// When resumable, this advances the cursor's previous index forward
+    /// past any interval whose end is still behind `window.start`, which is
+    /// a single forward pass rather than a fresh top-down descent through
+    /// the fast lanes. `push` growing the slow lane between calls is fine;
+    /// `rebuild_top_level` changing the number of fast lanes (which shifts
+    /// what `lane.interval` means at a given index) or the window
+    /// regressing below the cursor both force a fall back to
+    /// `first_overlap`.
+    fn seek(&self, window: Interval<T>, cursor: &mut Cursor<T>) -> usize {
+        let length = self.slow_lane.len();
+
+        let resumable = cursor.fast_lane_count == self.fast_lanes.len()
+            && match cursor.window {
+                Some(w) => window.start >= w.start,
+                None => false,
+            };
+
+        let index = if resumable {
+            let mut index = cursor.index;
+            while index < length
+                && self.slow_lane.intervals[index].end < window.start
+            {
+                index += 1;
+            }
+            index
+        } else {
+            self.first_overlap(window)
+        };
+
+        cursor.window = Some(window);
+        cursor.fast_lane_count = self.fast_lanes.len();
+        cursor.index = index;
+
+        index
+    }
+
     pub fn aggregate<I>(&self, window: I) -> A
     where
-        I: Into<Interval>,
+        I: Into<Interval<T>>,
+    {
+        let mut visitor = AggregateVisitor {
+            output: A::empty(),
+            phantom: PhantomData,
+        };
+        self.query_with(window, &mut visitor);
+        visitor.output
+    }
+
+    /// Like `aggregate`, but resumes scanning from `cursor` instead of
+    /// starting over at the top of the fast lanes. Intended for a stream of
+    /// windows with non-decreasing starts (see `Cursor`).
+    pub fn aggregate_from<I>(&self, window: I, cursor: &mut Cursor<T>) -> A
+    where
+        I: Into<Interval<T>>,
     {
         let mut visitor = AggregateVisitor {
             output: A::empty(),
             phantom: PhantomData,
         };
+        self.query_with_from(window, cursor, &mut visitor);
+        visitor.output
+    }
+
+    /// Like `aggregate`, but an interval that only partially straddles a
+    /// window boundary contributes `overlap_len / interval_len` of its
+    /// duration instead of the full amount.
+    ///
+    /// Fast-lane buckets are never partial (`scan_from` only visits one via
+    /// `window.contains(bucket)`), so they're folded in at weight 1.0;
+    /// every boundary-straddling interval is necessarily reached through
+    /// `visit_slow_lane` and weighted individually. Zero-length intervals
+    /// are weighted 1.0 to avoid a divide-by-zero.
+    pub fn aggregate_weighted<I>(&self, window: I) -> A
+    where
+        I: Into<Interval<T>>,
+        T: Duration,
+    {
+        let window = window.into();
+        let mut visitor = WeightedAggregateVisitor {
+            window,
+            output: A::empty(),
+            phantom: PhantomData,
+        };
         self.query_with(window, &mut visitor);
         visitor.output
     }
 
+    /// The length of the *union* of all intervals intersecting `window` —
+    /// each point in time counted once, rather than `aggregate`'s sum of
+    /// durations, which double-counts time covered by overlapping or
+    /// nested intervals (e.g. call-stack spans).
+    ///
+    /// A fast-lane bucket's pre-aggregated total can't be reused here:
+    /// union length isn't additively mergeable the way a sum is. So
+    /// `CoverageVisitor` decomposes every visited bucket back down to its
+    /// individual slow-lane intervals — which are already start-sorted —
+    /// and sweeps them in order, merging overlapping/adjacent runs and
+    /// flushing a run's length once a gap opens up before the next one.
+    pub fn covered_duration<I>(&self, window: I) -> T
+    where
+        I: Into<Interval<T>>,
+        T: Duration,
+    {
+        let window = window.into();
+        let mut visitor = CoverageVisitor {
+            window,
+            slow_lane: &self.slow_lane,
+            current: None,
+            total: T::default(),
+        };
+        self.query_with(window, &mut visitor);
+
+        if let Some(run) = visitor.current {
+            visitor.total = visitor.total + (run.end - run.start);
+        }
+        visitor.total
+    }
+
     pub fn query<I>(&self, window: I) -> impl Iterator<Item = &[V]>
     where
-        I: Into<Interval>,
+        I: Into<Interval<T>>,
     {
         let mut visitor = RangeVisitor {
             slow_lane: &self.slow_lane,
@@ -208,15 +363,66 @@ where
             .map(move |range| &self.slow_lane.values[range])
     }
 
+    /// Like `query`, but resumes scanning from `cursor` instead of starting
+    /// over at the top of the fast lanes. Intended for a stream of windows
+    /// with non-decreasing starts (see `Cursor`).
+    pub fn query_from<I>(
+        &self,
+        window: I,
+        cursor: &mut Cursor<T>,
+    ) -> impl Iterator<Item = &[V]>
+    where
+        I: Into<Interval<T>>,
+    {
+        let mut visitor = RangeVisitor {
+            slow_lane: &self.slow_lane,
+            output: vec![],
+            count: 0,
+        };
+
+        self.query_with_from(window, cursor, &mut visitor);
+
+        visitor
+            .output
+            .into_iter()
+            .map(move |range| &self.slow_lane.values[range])
+    }
+
     pub fn query_with<I, Q>(&self, window: I, visitor: &mut Q)
     where
-        I: Into<Interval>,
-        Q: QueryVisitor<V, A>,
+        I: Into<Interval<T>>,
+        Q: QueryVisitor<V, A, T>,
     {
         let window = window.into();
-        let length = self.slow_lane.intervals.len();
+        let index = self.first_overlap(window);
+        self.scan_from(window, index, visitor);
+    }
 
-        let mut index = self.first_overlap(window);
+    /// Like `query_with`, but resumes scanning from `cursor` instead of
+    /// starting over at the top of the fast lanes.
+    pub fn query_with_from<I, Q>(
+        &self,
+        window: I,
+        cursor: &mut Cursor<T>,
+        visitor: &mut Q,
+    ) where
+        I: Into<Interval<T>>,
+        Q: QueryVisitor<V, A, T>,
+    {
+        let window = window.into();
+        let index = self.seek(window, cursor);
+        self.scan_from(window, index, visitor);
+    }
+
+    fn scan_from<Q>(
+        &self,
+        window: Interval<T>,
+        mut index: usize,
+        visitor: &mut Q,
+    ) where
+        Q: QueryVisitor<V, A, T>,
+    {
+        let length = self.slow_lane.intervals.len();
 
         'search: while index < length {
             // Try and advance the index along the highest/coarsest fast lane.
@@ -255,27 +461,96 @@ where
     }
 }
 
-impl<V, A> QueryVisitor<V, A> for AggregateVisitor<V, A>
+impl<V, A, T> QueryVisitor<V, A, T> for AggregateVisitor<V, A>
 where
-    A: Aggregate<Value = V>,
+    A: Aggregate<T, Value = V>,
 {
-    fn visit_fast_lane(&mut self, lane: &FastLane<V, A>, index: usize) {
+    fn visit_fast_lane(&mut self, lane: &FastLane<V, A, T>, index: usize) {
         let lane_index = index / lane.interval;
         self.output.aggregate(&lane.aggregations[lane_index]);
     }
 
-    fn visit_slow_lane(&mut self, lane: &SlowLane<V>, index: usize) {
+    fn visit_slow_lane(&mut self, lane: &SlowLane<V, T>, index: usize) {
         let interval = &lane.intervals[index];
         let value = &lane.values[index];
         self.output.aggregate(&A::initial(interval, value));
     }
 }
 
-impl<'a, V, A> QueryVisitor<V, A> for RangeVisitor<'a, V>
+impl<V, A, T> QueryVisitor<V, A, T> for WeightedAggregateVisitor<V, A, T>
 where
-    A: Aggregate<Value = V>,
+    T: Duration,
+    A: Aggregate<T, Value = V>,
 {
-    fn visit_fast_lane(&mut self, lane: &FastLane<V, A>, index: usize) {
+    fn visit_fast_lane(&mut self, lane: &FastLane<V, A, T>, index: usize) {
+        let lane_index = index / lane.interval;
+        self.output.aggregate(&lane.aggregations[lane_index]);
+    }
+
+    fn visit_slow_lane(&mut self, lane: &SlowLane<V, T>, index: usize) {
+        let interval = &lane.intervals[index];
+        let value = &lane.values[index];
+        let mut aggregate = A::initial(interval, value);
+
+        let interval_len = interval.end - interval.start;
+        let weight = if interval_len == T::default() {
+            1.0
+        } else {
+            let overlap_start = interval.start.max(self.window.start);
+            let overlap_end = interval.end.min(self.window.end);
+            let overlap_len = overlap_end - overlap_start;
+            (overlap_len.to_f64() / interval_len.to_f64()) as f32
+        };
+
+        aggregate.weight(weight);
+        self.output.aggregate(&aggregate);
+    }
+}
+
+impl<'a, V, T> CoverageVisitor<'a, V, T>
+where
+    T: Duration,
+{
+    fn merge(&mut self, index: usize) {
+        let interval = &self.slow_lane.intervals[index];
+        let start = interval.start.max(self.window.start);
+        let end = interval.end.min(self.window.end);
+
+        self.current = Some(match self.current {
+            Some(run) if start <= run.end => Interval {
+                start: run.start,
+                end: run.end.max(end),
+            },
+            Some(run) => {
+                self.total = self.total + (run.end - run.start);
+                Interval { start, end }
+            }
+            None => Interval { start, end },
+        });
+    }
+}
+
+impl<'a, V, A, T> QueryVisitor<V, A, T> for CoverageVisitor<'a, V, T>
+where
+    T: Duration,
+{
+    fn visit_fast_lane(&mut self, lane: &FastLane<V, A, T>, index: usize) {
+        let end = (index + lane.interval).min(self.slow_lane.len());
+        for i in index..end {
+            self.merge(i);
+        }
+    }
+
+    fn visit_slow_lane(&mut self, _: &SlowLane<V, T>, index: usize) {
+        self.merge(index);
+    }
+}
+
+impl<'a, V, A, T> QueryVisitor<V, A, T> for RangeVisitor<'a, V, T>
+where
+    A: Aggregate<T, Value = V>,
+{
+    fn visit_fast_lane(&mut self, lane: &FastLane<V, A, T>, index: usize) {
         let end = (index + lane.interval).min(self.slow_lane.len());
         self.count += end - index;
         match self.output.last_mut() {
@@ -284,7 +559,7 @@ where
         };
     }
 
-    fn visit_slow_lane(&mut self, _: &SlowLane<V>, index: usize) {
+    fn visit_slow_lane(&mut self, _: &SlowLane<V, T>, index: usize) {
         let end = index + 1;
         self.count += 1;
         match self.output.last_mut() {
@@ -293,3 +568,94 @@ where
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregate::DefaultStatistics;
+
+    type Index = IntervalIndex<u64, DefaultStatistics<u64>>;
+
+    fn assert_matches_plain(
+        index: &Index,
+        window: (u64, u64),
+        actual: &DefaultStatistics<u64>,
+    ) {
+        let expected = index.aggregate(window);
+        assert_eq!(actual.count, expected.count, "window {:?}", window);
+        assert_eq!(actual.min, expected.min, "window {:?}", window);
+        assert_eq!(actual.max, expected.max, "window {:?}", window);
+        assert_eq!(
+            actual.total_duration, expected.total_duration,
+            "window {:?}",
+            window
+        );
+    }
+
+    #[test]
+    fn test_cursor_matches_plain_query_for_ordered_windows() {
+        let mut index = Index::new(2);
+        for i in 0..300u64 {
+            index.push((i * 10, i * 10 + 5), i);
+        }
+
+        let mut cursor = Cursor::new();
+        for start in (0..3000u64).step_by(7) {
+            let window = (start, start + 20);
+            let actual = index.aggregate_from(window, &mut cursor);
+            assert_matches_plain(&index, window, &actual);
+        }
+    }
+
+    #[test]
+    fn test_cursor_handles_regression_and_mid_stream_push() {
+        let mut index = Index::new(2);
+        for i in 0..50u64 {
+            index.push((i * 10, i * 10 + 5), i);
+        }
+
+        let mut cursor = Cursor::new();
+
+        // Advance the cursor forward.
+        let window = (100u64, 150u64);
+        let actual = index.aggregate_from(window, &mut cursor);
+        assert_matches_plain(&index, window, &actual);
+
+        // Push enough intervals to trigger `rebuild_top_level` (which
+        // changes `fast_lanes.len()`), then regress below the cursor's
+        // last window. Both must force a fall back to `first_overlap`
+        // rather than resuming from a now-stale cursor.
+        let lanes_before = index.fast_lanes.len();
+        for i in 50..600u64 {
+            index.push((i * 10, i * 10 + 5), i);
+        }
+        assert!(index.fast_lanes.len() > lanes_before);
+
+        let window = (50u64, 80u64);
+        let actual = index.aggregate_from(window, &mut cursor);
+        assert_matches_plain(&index, window, &actual);
+
+        // Resume forward again past the regression.
+        let window = (2000u64, 2050u64);
+        let actual = index.aggregate_from(window, &mut cursor);
+        assert_matches_plain(&index, window, &actual);
+    }
+
+    #[test]
+    fn test_covered_duration_deduplicates_overlap_and_clamps_to_window() {
+        let mut index = Index::new(4);
+
+        // [0,10) and [5,15) overlap -> union [0,15), length 15.
+        index.push((0u64, 10u64), 0u64);
+        index.push((5u64, 15u64), 1u64);
+        // [25,30) nests inside [20,40) -> union [20,40), length 20.
+        index.push((20u64, 40u64), 2u64);
+        index.push((25u64, 30u64), 3u64);
+        // [50,55) is disjoint from everything before it, and the query
+        // window clamps it down to [50,52), length 2.
+        index.push((50u64, 55u64), 4u64);
+
+        let covered = index.covered_duration((0u64, 52u64));
+        assert_eq!(covered, 15 + 20 + 2);
+    }
+}
@@ -1,13 +1,22 @@
 pub type Timestamp = u64;
 
+/// `Interval<Timestamp>`, the coordinate type this crate shipped with
+/// before `Interval` became generic over `T`.
+pub type TimeInterval = Interval<Timestamp>;
+
+/// `T` must be a totally-ordered coordinate (e.g. `u32`, `u64`, `i64`) so
+/// that `start`/`end` can be compared and the index's fast lanes can be
+/// bucketed by `min`/`max`. Floating-point coordinates like `f64` don't
+/// qualify — `NAN` breaks the total order `Ord` requires — so they aren't
+/// supported.
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Ord, Eq)]
-pub struct Interval {
-    pub start: Timestamp,
-    pub end: Timestamp,
+pub struct Interval<T = Timestamp> {
+    pub start: T,
+    pub end: T,
 }
 
-impl From<(Timestamp, Timestamp)> for Interval {
-    fn from(i: (Timestamp, Timestamp)) -> Interval {
+impl<T> From<(T, T)> for Interval<T> {
+    fn from(i: (T, T)) -> Interval<T> {
         Interval {
             start: i.0,
             end: i.1,
@@ -15,14 +24,14 @@ impl From<(Timestamp, Timestamp)> for Interval {
     }
 }
 
-impl From<&Interval> for Interval {
-    fn from(interval: &Interval) -> Interval {
+impl<T: Copy> From<&Interval<T>> for Interval<T> {
+    fn from(interval: &Interval<T>) -> Interval<T> {
         *interval
     }
 }
 
-impl Interval {
-    pub fn new(start: Timestamp, end: Timestamp) -> Self {
+impl<T: Copy + Ord> Interval<T> {
+    pub fn new(start: T, end: T) -> Self {
         Interval { start, end }
     }
 
@@ -3,7 +3,7 @@ mod baseline;
 mod index;
 mod interval;
 
-pub use aggregate::{Aggregate, DefaultStatistics};
+pub use aggregate::{Aggregate, DefaultStatistics, Duration, HistogramStatistics};
 pub use baseline::{load_test_file, BaselineIntervalIndex};
-pub use index::IntervalIndex;
-pub use interval::Interval;
+pub use index::{Cursor, IntervalIndex};
+pub use interval::{Interval, Timestamp, TimeInterval};